@@ -1,57 +1,159 @@
 #![allow(private_interfaces)]
 
 use std::fmt::Write;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use chrono::{Datelike, Local};
-use typst::diag::{FileError, FileResult};
+use rayon::prelude::*;
+use serde::Serialize;
+use typst::diag::{FileError, FileResult, Severity, SourceDiagnostic};
 use typst::foundations::{Bytes, Datetime};
+use typst::html::HtmlDocument;
 use typst::layout::PagedDocument;
+use typst::syntax::package::PackageSpec;
 use typst::syntax::{FileId, Source, VirtualPath};
-use typst::text::{Font, FontBook};
+use typst::text::{Font, FontBook, FontInfo};
 use typst::utils::LazyHash;
 use typst::{Library, LibraryExt, World};
 
+/// Where a lazily-loaded font's bytes come from.
+enum FontSource {
+    /// Bytes already held in memory (bundled assets or caller-supplied fonts).
+    Memory(Bytes),
+    /// A face that must be read from disk on first use (system fonts).
+    File(PathBuf),
+}
+
+/// A font face whose metadata is known up front but whose full `Font` (and the
+/// parsing that builds it) is only materialized the first time it's requested.
+struct FontSlot {
+    source: FontSource,
+    index: u32,
+    font: OnceLock<Option<Font>>,
+}
+
+impl FontSlot {
+    fn get(&self) -> Option<Font> {
+        self.font
+            .get_or_init(|| match &self.source {
+                FontSource::Memory(bytes) => Font::new(bytes.clone(), self.index),
+                FontSource::File(path) => {
+                    let data = std::fs::read(path).ok()?;
+                    Font::new(Bytes::new(data), self.index)
+                }
+            })
+            .clone()
+    }
+}
+
+/// Walks the standard per-platform font directories (plus any caller-supplied
+/// ones) via `fontdb` and registers every file-backed face's metadata. Each
+/// face's file is read once here to build its `FontInfo`; the bytes are not
+/// kept, and the file is read again when the `Font` is first requested.
+struct FontSearcher;
+
+impl FontSearcher {
+    fn search(extra_dirs: &[PathBuf]) -> Vec<(FontInfo, FontSlot)> {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        for dir in extra_dirs {
+            db.load_fonts_dir(dir);
+        }
+
+        let mut found = Vec::new();
+        for face in db.faces() {
+            let fontdb::Source::File(path) = &face.source else {
+                // Only file-backed faces can be loaded lazily later.
+                continue;
+            };
+            let Some(Some(info)) =
+                db.with_face_data(face.id, |data, index| FontInfo::new(data, index))
+            else {
+                continue;
+            };
+            found.push((
+                info,
+                FontSlot {
+                    source: FontSource::File(path.clone()),
+                    index: face.index,
+                    font: OnceLock::new(),
+                },
+            ));
+        }
+        found
+    }
+}
+
 /// Shared, immutable resources owned by a compiler instance.
 struct SharedResources {
     library: LazyHash<Library>,
     book: LazyHash<FontBook>,
-    fonts: Vec<Font>,
+    fonts: Vec<FontSlot>,
     main_id: FileId,
 }
 
 impl SharedResources {
-    fn new(custom_font_data: &[&[u8]]) -> Self {
-        // Pre-allocate: bundled fonts typically yield ~20 faces,
-        // each custom file usually contains 1-4 faces.
-        let mut fonts = Vec::with_capacity(20 + custom_font_data.len() * 4);
+    fn new(
+        custom_font_data: &[&[u8]],
+        extra_font_dirs: &[PathBuf],
+        use_system_fonts: bool,
+    ) -> Self {
+        let mut book = FontBook::new();
+        let mut fonts = Vec::new();
 
-        // Load bundled fonts.
+        // Bundled fonts: extract metadata now, defer decoding the full `Font`
+        // (and its shaping tables) until the face is actually used.
         for data in typst_assets::fonts() {
             let bytes = Bytes::new(data);
-            for index in 0.. {
-                match Font::new(bytes.clone(), index) {
-                    Some(font) => fonts.push(font),
+            for index in 0u32.. {
+                match FontInfo::new(&bytes, index) {
+                    Some(info) => {
+                        book.push(info);
+                        fonts.push(FontSlot {
+                            source: FontSource::Memory(bytes.clone()),
+                            index,
+                            font: OnceLock::new(),
+                        });
+                    }
                     None => break,
                 }
             }
         }
 
-        // Load custom fonts.
+        // Caller-supplied fonts, same lazy treatment.
         for data in custom_font_data {
             let bytes = Bytes::new(data.to_vec());
-            for index in 0.. {
-                match Font::new(bytes.clone(), index) {
-                    Some(font) => fonts.push(font),
+            for index in 0u32.. {
+                match FontInfo::new(&bytes, index) {
+                    Some(info) => {
+                        book.push(info);
+                        fonts.push(FontSlot {
+                            source: FontSource::Memory(bytes.clone()),
+                            index,
+                            font: OnceLock::new(),
+                        });
+                    }
                     None => break,
                 }
             }
         }
 
-        let mut book = FontBook::new();
-        for font in &fonts {
-            book.push(font.info().clone());
+        // System fonts, read only for metadata here; the `Font` itself is
+        // loaded from disk on first use. Opt-in: which
+        // faces exist varies by host, so pulling them in by default would make
+        // font selection (and thus layout) depend on the machine running the
+        // compile, undermining byte-reproducible output (see the
+        // `source_date_epoch` section of `typst_world_compile`'s doc comment).
+        if use_system_fonts {
+            for (info, slot) in FontSearcher::search(extra_font_dirs) {
+                book.push(info);
+                fonts.push(slot);
+            }
         }
 
         SharedResources {
@@ -70,6 +172,8 @@ struct SingleSourceWorld<'a> {
     root: Option<PathBuf>,
     canonical_root: Option<PathBuf>,
     package_cache: Option<PathBuf>,
+    allow_package_download: bool,
+    source_date_epoch: Option<i64>,
 }
 
 impl<'a> SingleSourceWorld<'a> {
@@ -78,6 +182,8 @@ impl<'a> SingleSourceWorld<'a> {
         source_text: String,
         root: Option<PathBuf>,
         package_cache: Option<PathBuf>,
+        allow_package_download: bool,
+        source_date_epoch: Option<i64>,
     ) -> Self {
         // Pre-compute canonical root once to avoid repeated canonicalize() in resolve_path.
         let canonical_root = root.as_ref().and_then(|r| r.canonicalize().ok());
@@ -87,6 +193,8 @@ impl<'a> SingleSourceWorld<'a> {
             root,
             canonical_root,
             package_cache,
+            allow_package_download,
+            source_date_epoch,
         }
     }
 
@@ -104,6 +212,11 @@ impl<'a> SingleSourceWorld<'a> {
                 .join(pkg.namespace.as_str())
                 .join(pkg.name.as_str())
                 .join(pkg.version.to_string());
+            if !b.exists() && self.allow_package_download {
+                // Best-effort: if the download fails, fall through and let the
+                // canonicalize() below report the familiar NotFound.
+                let _ = download_package(cache, pkg);
+            }
             let cb = b
                 .canonicalize()
                 .map_err(|_| FileError::NotFound(vpath.into()))?;
@@ -139,6 +252,63 @@ impl<'a> SingleSourceWorld<'a> {
     }
 }
 
+/// Monotonic counter mixed with the process id to keep concurrent downloads'
+/// temp directories unique even if several land in the same call.
+static DOWNLOAD_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Download a `@namespace/name:version` package archive from the Typst package
+/// registry into `{cache}/{namespace}/{name}/{version}/`.
+///
+/// Downloads into a sibling temp directory and renames it into place atomically
+/// so concurrent compiles never observe a half-written cache entry.
+fn download_package(cache: &Path, pkg: &PackageSpec) -> io::Result<()> {
+    let url = format!(
+        "https://packages.typst.org/{}/{}-{}.tar.gz",
+        pkg.namespace, pkg.name, pkg.version
+    );
+
+    // Bound both connect and total time so a hung or slow registry can't block
+    // the calling thread (and, via the batch FFI, a whole rayon worker pool)
+    // indefinitely.
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+        .build();
+
+    let response = agent
+        .get(&url)
+        .call()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    let name_dir = cache.join(pkg.namespace.as_str()).join(pkg.name.as_str());
+    std::fs::create_dir_all(&name_dir)?;
+
+    let pid = std::process::id();
+    let seq = DOWNLOAD_SEQ.fetch_add(1, Ordering::Relaxed);
+    let tmp_dir = name_dir.join(format!(".{}-{}-{}.tmp", pkg.version, pid, seq));
+    std::fs::create_dir_all(&tmp_dir)?;
+
+    let gz = flate2::read::GzDecoder::new(response.into_reader());
+    let mut archive = tar::Archive::new(gz);
+    let unpack_result = archive.unpack(&tmp_dir);
+    if let Err(e) = unpack_result {
+        let _ = std::fs::remove_dir_all(&tmp_dir);
+        return Err(e);
+    }
+
+    let version_dir = name_dir.join(pkg.version.to_string());
+    // Another concurrent download may have already finished; that's fine.
+    match std::fs::rename(&tmp_dir, &version_dir) {
+        Ok(()) => Ok(()),
+        Err(e) if version_dir.exists() => {
+            let _ = std::fs::remove_dir_all(&tmp_dir);
+            let _ = e;
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
 impl World for SingleSourceWorld<'_> {
     fn library(&self) -> &LazyHash<Library> {
         &self.shared.library
@@ -170,16 +340,40 @@ impl World for SingleSourceWorld<'_> {
     }
 
     fn font(&self, index: usize) -> Option<Font> {
-        self.shared.fonts.get(index).cloned()
+        self.shared.fonts.get(index)?.get()
     }
 
+    // FOLLOWUP (chunk0-6): the backlog item that introduced source_date_epoch
+    // was framed as also fixing zones whose UTC offset isn't a whole number
+    // of hours. That part is not delivered here -- `offset` below is exactly
+    // the `Option<i64>` whole-hours parameter typst::World::today hands us,
+    // pinning the clock doesn't change what that parameter can represent.
+    // Supporting fractional-hour zones would need a signature change on the
+    // `World` trait itself; re-scope with whoever filed the request before
+    // treating this item as closed.
     fn today(&self, offset: Option<i64>) -> Option<Datetime> {
-        let now = Local::now();
-        let naive = match offset {
-            None => now.naive_local(),
-            Some(o) => {
-                let utc = now.naive_utc();
-                utc + chrono::Duration::hours(o)
+        let naive = if let Some(epoch) = self.source_date_epoch {
+            // Deterministic clock: derive the date from the pinned instant
+            // instead of the wall clock, so the same source compiles to the
+            // same date every time. `offset` is `typst::World::today`'s own
+            // whole-hours parameter (driven by the document's own
+            // `datetime.today(offset: ...)` calls) — this can't represent fractional-
+            // hour zones any more than the wall-clock branch below could,
+            // since that limitation lives in the `World` trait's signature,
+            // not in how "now" is obtained.
+            let instant = chrono::DateTime::from_timestamp(epoch, 0)?;
+            match offset {
+                None => instant.naive_utc(),
+                Some(o) => instant.naive_utc() + chrono::Duration::hours(o),
+            }
+        } else {
+            let now = Local::now();
+            match offset {
+                None => now.naive_local(),
+                Some(o) => {
+                    let utc = now.naive_utc();
+                    utc + chrono::Duration::hours(o)
+                }
             }
         };
         Datetime::from_ymd(
@@ -204,18 +398,61 @@ pub struct TypstResult {
     pub len: usize,
     /// 0 = success, 1 = error.
     pub error: i32,
+    /// Number of pages packed into `data` for the per-page formats
+    /// (`TYPST_FORMAT_SVG`/`TYPST_FORMAT_PNG`). 0 for single-buffer formats.
+    pub page_count: usize,
+    /// Byte length of each page within `data`, in order, `page_count` entries long.
+    /// Null when `page_count` is 0.
+    pub page_lengths: *mut u64,
+    /// JSON array of structured diagnostics (severity, message, file, byte
+    /// range, hints), populated only when the caller requested
+    /// `structured_diagnostics` and there was at least one diagnostic.
+    /// Null/0 otherwise; `data`/`len` always carry the flat-string message
+    /// on error regardless of this field.
+    pub diagnostics: *mut u8,
+    pub diagnostics_len: usize,
 }
 
+/// Array of per-job `TypstResult`s from `typst_world_compile_batch`.
+#[repr(C)]
+pub struct TypstResults {
+    pub results: *mut TypstResult,
+    pub count: usize,
+}
+
+/// Output format selector for `typst_world_compile`.
+pub const TYPST_FORMAT_PDF: u8 = 0;
+/// Output format selector for `typst_world_compile`.
+pub const TYPST_FORMAT_HTML: u8 = 1;
+/// Output format selector for `typst_world_compile`: one SVG document per page.
+pub const TYPST_FORMAT_SVG: u8 = 2;
+/// Output format selector for `typst_world_compile`: one rasterized PNG per page.
+pub const TYPST_FORMAT_PNG: u8 = 3;
+
 /// Create a new compiler instance with optional custom fonts.
 ///
+/// When `use_system_fonts` is true, this additionally discovers fonts
+/// installed on the host (via `fontdb`'s standard per-platform search paths)
+/// plus any directories listed in `font_dir_ptrs`/`font_dir_lens`; discovery
+/// reads each font file to extract face metadata without keeping its bytes,
+/// and the font is read and decoded again on first use.
+/// This is opt-in: which faces exist varies by host, so enabling it means
+/// font selection (and therefore layout) can differ between machines — pass
+/// `false` for byte-reproducible output across hosts/containers.
+///
 /// # Safety
 /// Each `font_ptrs[i]` must point to `font_lens[i]` valid bytes.
+/// Each `font_dir_ptrs[i]` must point to `font_dir_lens[i]` valid UTF-8 bytes.
 /// Returns a heap-allocated handle. Free with `typst_world_free`.
 #[no_mangle]
 pub unsafe extern "C" fn typst_world_new(
     font_ptrs: *const *const u8,
     font_lens: *const usize,
     font_count: usize,
+    font_dir_ptrs: *const *const u8,
+    font_dir_lens: *const usize,
+    font_dir_count: usize,
+    use_system_fonts: bool,
 ) -> *mut TypstWorld {
     let custom: Vec<&[u8]> = if font_count > 0 && !font_ptrs.is_null() && !font_lens.is_null() {
         let ptrs = unsafe { slice::from_raw_parts(font_ptrs, font_count) };
@@ -228,11 +465,52 @@ pub unsafe extern "C" fn typst_world_new(
         Vec::new()
     };
 
-    let resources = SharedResources::new(&custom);
+    let extra_font_dirs: Vec<PathBuf> =
+        if font_dir_count > 0 && !font_dir_ptrs.is_null() && !font_dir_lens.is_null() {
+            let ptrs = unsafe { slice::from_raw_parts(font_dir_ptrs, font_dir_count) };
+            let lens = unsafe { slice::from_raw_parts(font_dir_lens, font_dir_count) };
+            ptrs.iter()
+                .zip(lens.iter())
+                .filter_map(|(&ptr, &len)| unsafe { parse_optional_path(ptr, len) })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+    let resources = SharedResources::new(&custom, &extra_font_dirs, use_system_fonts);
     Box::into_raw(Box::new(resources))
 }
 
-/// Compile a Typst source string to PDF using the given compiler instance.
+/// Compile a Typst source string using the given compiler instance.
+///
+/// `format` selects the output: `TYPST_FORMAT_PDF`, `TYPST_FORMAT_HTML`,
+/// `TYPST_FORMAT_SVG`, or `TYPST_FORMAT_PNG`. Unknown values fall back to PDF.
+/// `pixels_per_point` controls rasterization density for `TYPST_FORMAT_PNG`
+/// (e.g. `144.0 / 72.0` for 144 DPI) and is ignored by the other formats. A
+/// page whose rasterized size would exceed `MAX_PNG_DIMENSION_PX` in either
+/// dimension is rejected with an error instead of being rendered.
+/// `allow_package_download`, when true, fetches missing `@preview` (and other
+/// namespace) packages from the Typst package registry into `pkg_ptr`'s cache
+/// directory on demand; offline/sandboxed callers should pass `false` to keep
+/// strictly-local resolution.
+/// `has_source_date_epoch`/`source_date_epoch` pin the compilation date to a
+/// Unix timestamp instead of the wall clock, removing that source of
+/// non-determinism; pass `has_source_date_epoch = false` to keep using the
+/// current time. For fully byte-reproducible output across hosts, also
+/// create the `TypstWorld` with `use_system_fonts = false` (see
+/// `typst_world_new`) — otherwise font selection, and therefore layout, can
+/// still vary with whatever happens to be installed on the machine. Note
+/// that pinning the epoch does not fix `datetime.today()` for zones whose offset
+/// isn't a whole number of hours — see the `FOLLOWUP` note on
+/// `SingleSourceWorld::today`; that part of this feature's original request
+/// remains open.
+/// `structured_diagnostics`, when true, additionally populates the result's
+/// `diagnostics`/`diagnostics_len` with a JSON array of every warning/error
+/// (severity, message, file path, byte range, hints) alongside the existing
+/// `data`/`len` output, which keeps carrying the flat-string message on error
+/// regardless of this flag. Per-page export failures of the SVG/PNG formats
+/// (such as the PNG size limit) are not source diagnostics and appear only in
+/// the flat-string message.
 ///
 /// # Safety
 /// - `world` must be a valid pointer from `typst_world_new`.
@@ -249,6 +527,12 @@ pub unsafe extern "C" fn typst_world_compile(
     root_len: usize,
     pkg_ptr: *const u8,
     pkg_len: usize,
+    format: u8,
+    pixels_per_point: f32,
+    allow_package_download: bool,
+    source_date_epoch: i64,
+    has_source_date_epoch: bool,
+    structured_diagnostics: bool,
 ) -> TypstResult {
     let shared = unsafe { &*world };
 
@@ -260,28 +544,85 @@ pub unsafe extern "C" fn typst_world_compile(
         }
     };
 
-    let root = if !root_ptr.is_null() && root_len > 0 {
-        let bytes = unsafe { slice::from_raw_parts(root_ptr, root_len) };
-        match std::str::from_utf8(bytes) {
-            Ok(s) => Some(PathBuf::from(s)),
-            Err(_) => None,
-        }
-    } else {
-        None
-    };
+    let root = unsafe { parse_optional_path(root_ptr, root_len) };
+    let package_cache = unsafe { parse_optional_path(pkg_ptr, pkg_len) };
 
-    let package_cache = if !pkg_ptr.is_null() && pkg_len > 0 {
-        let bytes = unsafe { slice::from_raw_parts(pkg_ptr, pkg_len) };
-        match std::str::from_utf8(bytes) {
-            Ok(s) => Some(PathBuf::from(s)),
-            Err(_) => None,
-        }
+    let world = SingleSourceWorld::new(
+        shared,
+        source_text,
+        root,
+        package_cache,
+        allow_package_download,
+        has_source_date_epoch.then_some(source_date_epoch),
+    );
+
+    dispatch_format(&world, format, pixels_per_point, structured_diagnostics)
+}
+
+/// Run the compile job for `world` through the exporter selected by `format`.
+fn dispatch_format(
+    world: &SingleSourceWorld,
+    format: u8,
+    pixels_per_point: f32,
+    structured_diagnostics: bool,
+) -> TypstResult {
+    match format {
+        TYPST_FORMAT_HTML => compile_html(world, structured_diagnostics),
+        TYPST_FORMAT_SVG => compile_svg(world, structured_diagnostics),
+        TYPST_FORMAT_PNG => compile_png(world, pixels_per_point, structured_diagnostics),
+        _ => compile_pdf(world, structured_diagnostics),
+    }
+}
+
+/// Build a slice from an FFI pointer/length pair, treating a null pointer as
+/// an empty slice regardless of `len`. A zero-length slice passed from Go
+/// commonly carries a nil backing pointer, which `slice::from_raw_parts`
+/// does not allow even at length 0.
+///
+/// # Safety
+/// If `ptr` is non-null, it must point to `len` valid `T`s.
+unsafe fn safe_slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if len == 0 || ptr.is_null() {
+        &[]
     } else {
-        None
-    };
+        unsafe { slice::from_raw_parts(ptr, len) }
+    }
+}
+
+/// Parse an optional UTF-8 path argument from an FFI pointer/length pair.
+/// Returns `None` for a null/empty pointer or invalid UTF-8.
+///
+/// # Safety
+/// `ptr` must point to `len` valid bytes, or be null.
+unsafe fn parse_optional_path(ptr: *const u8, len: usize) -> Option<PathBuf> {
+    if ptr.is_null() || len == 0 {
+        return None;
+    }
+    let bytes = unsafe { slice::from_raw_parts(ptr, len) };
+    std::str::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Flatten warnings and errors into the repo's single `"warning: ..."` /
+/// `"<context> error: ..."` newline-joined message, shared by every export
+/// path's error formatting.
+fn format_diagnostics(
+    context: &str,
+    warnings: &[SourceDiagnostic],
+    errors: &[SourceDiagnostic],
+) -> String {
+    let mut msg = String::with_capacity((warnings.len() + errors.len()) * 64);
+    for w in warnings.iter() {
+        let _ = write!(msg, "warning: {}\n", w.message);
+    }
+    for err in errors.iter() {
+        let _ = write!(msg, "{context} error: {}\n", err.message);
+    }
+    msg
+}
 
-    let world = SingleSourceWorld::new(shared, source_text, root, package_cache);
-    let result = typst::compile::<PagedDocument>(&world);
+/// Compile to PDF and serialize the result, folding warnings/errors into a flat message.
+fn compile_pdf(world: &SingleSourceWorld, structured_diagnostics: bool) -> TypstResult {
+    let result = typst::compile::<PagedDocument>(world);
 
     match result.output {
         Ok(document) => {
@@ -290,42 +631,232 @@ pub unsafe extern "C" fn typst_world_compile(
                 ..typst_pdf::PdfOptions::default()
             };
             match typst_pdf::pdf(&document, &options) {
-                Ok(pdf_bytes) => {
-                    // Leak the PDF bytes into C-owned memory; Go will free via typst_free_result.
-                    let mut boxed = pdf_bytes.into_boxed_slice();
-                    let ptr = boxed.as_mut_ptr();
-                    let len = boxed.len();
-                    std::mem::forget(boxed);
-                    TypstResult {
-                        data: ptr,
-                        len,
-                        error: 0,
-                    }
-                }
+                Ok(pdf_bytes) => attach_diagnostics(
+                    world,
+                    structured_diagnostics,
+                    &result.warnings,
+                    &[],
+                    leak_bytes(pdf_bytes),
+                ),
                 Err(errors) => {
-                    let mut msg =
-                        String::with_capacity((result.warnings.len() + errors.len()) * 64);
-                    for w in result.warnings.iter() {
-                        let _ = write!(msg, "warning: {}\n", w.message);
-                    }
-                    for err in errors.iter() {
-                        let _ = write!(msg, "pdf export error: {}\n", err.message);
-                    }
-                    make_error(msg)
+                    let msg = format_diagnostics("pdf export", &result.warnings, &errors);
+                    attach_diagnostics(
+                        world,
+                        structured_diagnostics,
+                        &result.warnings,
+                        &errors,
+                        make_error(msg),
+                    )
                 }
             }
         }
         Err(errors) => {
-            let mut msg = String::with_capacity((result.warnings.len() + errors.len()) * 64);
-            for w in result.warnings.iter() {
-                let _ = write!(msg, "warning: {}\n", w.message);
+            let msg = format_diagnostics("compile", &result.warnings, &errors);
+            attach_diagnostics(
+                world,
+                structured_diagnostics,
+                &result.warnings,
+                &errors,
+                make_error(msg),
+            )
+        }
+    }
+}
+
+/// Compile to HTML and serialize the result, folding warnings/errors into a flat message.
+fn compile_html(world: &SingleSourceWorld, structured_diagnostics: bool) -> TypstResult {
+    let result = typst::compile::<HtmlDocument>(world);
+
+    match result.output {
+        Ok(document) => match typst_html::html(&document) {
+            Ok(html) => attach_diagnostics(
+                world,
+                structured_diagnostics,
+                &result.warnings,
+                &[],
+                leak_bytes(html.into_bytes()),
+            ),
+            Err(errors) => {
+                let msg = format_diagnostics("html export", &result.warnings, &errors);
+                attach_diagnostics(
+                    world,
+                    structured_diagnostics,
+                    &result.warnings,
+                    &errors,
+                    make_error(msg),
+                )
             }
-            for err in errors.iter() {
-                let _ = write!(msg, "compile error: {}\n", err.message);
+        },
+        Err(errors) => {
+            let msg = format_diagnostics("compile", &result.warnings, &errors);
+            attach_diagnostics(
+                world,
+                structured_diagnostics,
+                &result.warnings,
+                &errors,
+                make_error(msg),
+            )
+        }
+    }
+}
+
+/// Compile and render each page to an independent SVG document.
+fn compile_svg(world: &SingleSourceWorld, structured_diagnostics: bool) -> TypstResult {
+    compile_paged(world, structured_diagnostics, |page| {
+        Ok(typst_svg::svg(page).into_bytes())
+    })
+}
+
+/// Upper bound on a rasterized page's width/height in pixels (page size in
+/// points times `pixels_per_point`). `pixels_per_point` arrives untrusted
+/// over FFI, so without a cap a caller-supplied DPI (or a document with a
+/// large custom page size) could demand a multi-gigabyte pixmap allocation
+/// before `encode_png` is ever reached.
+const MAX_PNG_DIMENSION_PX: f64 = 10_000.0;
+
+/// Compile and rasterize each page to an independent PNG image.
+fn compile_png(
+    world: &SingleSourceWorld,
+    pixels_per_point: f32,
+    structured_diagnostics: bool,
+) -> TypstResult {
+    let ppp = if pixels_per_point > 0.0 {
+        pixels_per_point
+    } else {
+        1.0
+    };
+    compile_paged(world, structured_diagnostics, move |page| {
+        let size = page.frame.size();
+        let width_px = size.x.to_pt() * ppp as f64;
+        let height_px = size.y.to_pt() * ppp as f64;
+        if width_px > MAX_PNG_DIMENSION_PX || height_px > MAX_PNG_DIMENSION_PX {
+            return Err(format!(
+                "png page size {width_px:.0}x{height_px:.0}px exceeds the {MAX_PNG_DIMENSION_PX:.0}px limit (reduce pixels_per_point or the page size)"
+            ));
+        }
+        typst_render::render(page, ppp)
+            .encode_png()
+            .map_err(|e| format!("png encode error: {e}"))
+    })
+}
+
+/// Shared per-page export path: compile to `PagedDocument`, then render every page
+/// through `encode` and pack the results into one length-prefixed `TypstResult`.
+/// An `Err` from `encode` on any page makes the whole result an error that
+/// lists every failing page (all pages are still attempted), rather than
+/// silently producing a blank page.
+fn compile_paged(
+    world: &SingleSourceWorld,
+    structured_diagnostics: bool,
+    encode: impl Fn(&typst::layout::Page) -> Result<Vec<u8>, String>,
+) -> TypstResult {
+    let result = typst::compile::<PagedDocument>(world);
+
+    match result.output {
+        Ok(document) => {
+            let mut pages = Vec::with_capacity(document.pages.len());
+            let mut encode_errors = Vec::new();
+            for (index, page) in document.pages.iter().enumerate() {
+                match encode(page) {
+                    Ok(bytes) => pages.push(bytes),
+                    Err(e) => encode_errors.push((index, e)),
+                }
+            }
+            if encode_errors.is_empty() {
+                attach_diagnostics(
+                    world,
+                    structured_diagnostics,
+                    &result.warnings,
+                    &[],
+                    leak_pages(pages),
+                )
+            } else {
+                let mut msg = format_diagnostics("compile", &result.warnings, &[]);
+                for (index, e) in encode_errors.iter() {
+                    let _ = write!(msg, "page {index} export error: {e}\n");
+                }
+                attach_diagnostics(
+                    world,
+                    structured_diagnostics,
+                    &result.warnings,
+                    &[],
+                    make_error(msg),
+                )
             }
-            make_error(msg)
         }
+        Err(errors) => {
+            let msg = format_diagnostics("compile", &result.warnings, &errors);
+            attach_diagnostics(
+                world,
+                structured_diagnostics,
+                &result.warnings,
+                &errors,
+                make_error(msg),
+            )
+        }
+    }
+}
+
+/// A single diagnostic (warning or error), resolved to a file path and byte
+/// range where possible, for `structured_diagnostics` JSON output.
+#[derive(Serialize)]
+struct DiagnosticJson {
+    severity: &'static str,
+    message: String,
+    path: Option<String>,
+    start: Option<usize>,
+    end: Option<usize>,
+    hints: Vec<String>,
+}
+
+/// If `structured_diagnostics` is set and there's at least one diagnostic,
+/// serialize `warnings` and `errors` to JSON and attach them to `out` alongside
+/// its existing (unchanged) `data`/`len` output.
+fn attach_diagnostics(
+    world: &SingleSourceWorld,
+    structured_diagnostics: bool,
+    warnings: &[SourceDiagnostic],
+    errors: &[SourceDiagnostic],
+    mut out: TypstResult,
+) -> TypstResult {
+    if !structured_diagnostics || (warnings.is_empty() && errors.is_empty()) {
+        return out;
     }
+
+    let entries: Vec<DiagnosticJson> = warnings
+        .iter()
+        .chain(errors.iter())
+        .map(|diag| {
+            let (path, range) = match diag.span.id() {
+                Some(id) => {
+                    let path = id.vpath().as_rootless_path().display().to_string();
+                    let range = world.source(id).ok().and_then(|src| src.range(diag.span));
+                    (Some(path), range)
+                }
+                None => (None, None),
+            };
+            DiagnosticJson {
+                severity: match diag.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+                message: diag.message.to_string(),
+                path,
+                start: range.as_ref().map(|r| r.start),
+                end: range.as_ref().map(|r| r.end),
+                hints: diag.hints.iter().map(|h| h.to_string()).collect(),
+            }
+        })
+        .collect();
+
+    let Ok(json) = serde_json::to_vec(&entries) else {
+        return out;
+    };
+    let mut boxed = json.into_boxed_slice();
+    out.diagnostics = boxed.as_mut_ptr();
+    out.diagnostics_len = boxed.len();
+    std::mem::forget(boxed);
+    out
 }
 
 /// Free a compiler instance.
@@ -342,13 +873,179 @@ pub unsafe extern "C" fn typst_world_free(world: *mut TypstWorld) {
 /// Free memory allocated by `typst_world_compile`.
 ///
 /// # Safety
-/// `data` and `len` must come from a previous `TypstResult`.
+/// `data`, `len`, `page_count`, `page_lengths`, `diagnostics` and `diagnostics_len`
+/// must come from the same previous `TypstResult`.
 #[no_mangle]
-pub unsafe extern "C" fn typst_free_result(data: *mut u8, len: usize) {
+pub unsafe extern "C" fn typst_free_result(
+    data: *mut u8,
+    len: usize,
+    page_count: usize,
+    page_lengths: *mut u64,
+    diagnostics: *mut u8,
+    diagnostics_len: usize,
+) {
     if !data.is_null() && len > 0 {
         // Reconstruct the Vec from the leaked pointer and drop it to free the memory.
         let _ = unsafe { Vec::from_raw_parts(data, len, len) };
     }
+    if !page_lengths.is_null() && page_count > 0 {
+        let _ = unsafe { Vec::from_raw_parts(page_lengths, page_count, page_count) };
+    }
+    if !diagnostics.is_null() && diagnostics_len > 0 {
+        let _ = unsafe { Vec::from_raw_parts(diagnostics, diagnostics_len, diagnostics_len) };
+    }
+}
+
+/// Compile a batch of independent jobs concurrently against a single shared
+/// `TypstWorld`, reusing its fonts/book/library across every job instead of
+/// paying font-loading cost per call. Each job gets its own `SingleSourceWorld`
+/// and the jobs are driven by a rayon parallel iterator.
+///
+/// Job `i` is described by `source_ptrs[i]`/`source_lens[i]`,
+/// `root_ptrs[i]`/`root_lens[i]` (optional, NULL/0 = disabled), and
+/// `pkg_ptrs[i]`/`pkg_lens[i]` (optional, NULL/0 = disabled). `format`,
+/// `pixels_per_point`, and `allow_package_download` apply to every job.
+///
+/// The `comemo` memoization cache used internally by Typst is process-global,
+/// so callers compiling many batches in a long-lived process should call
+/// `comemo::evict` periodically between batches to bound memory.
+///
+/// # Safety
+/// - `world` must be a valid pointer from `typst_world_new`.
+/// - `source_ptrs`/`source_lens`, `root_ptrs`/`root_lens`, and `pkg_ptrs`/`pkg_lens`
+///   must each point to `job_count` valid entries.
+/// - Free the result with `typst_free_results`.
+#[no_mangle]
+pub unsafe extern "C" fn typst_world_compile_batch(
+    world: *const TypstWorld,
+    source_ptrs: *const *const u8,
+    source_lens: *const usize,
+    root_ptrs: *const *const u8,
+    root_lens: *const usize,
+    pkg_ptrs: *const *const u8,
+    pkg_lens: *const usize,
+    job_count: usize,
+    format: u8,
+    pixels_per_point: f32,
+    allow_package_download: bool,
+    source_date_epoch: i64,
+    has_source_date_epoch: bool,
+    structured_diagnostics: bool,
+) -> TypstResults {
+    let shared = unsafe { &*world };
+
+    let source_ptrs = unsafe { safe_slice(source_ptrs, job_count) };
+    let source_lens = unsafe { safe_slice(source_lens, job_count) };
+    let root_ptrs = unsafe { safe_slice(root_ptrs, job_count) };
+    let root_lens = unsafe { safe_slice(root_lens, job_count) };
+    let pkg_ptrs = unsafe { safe_slice(pkg_ptrs, job_count) };
+    let pkg_lens = unsafe { safe_slice(pkg_lens, job_count) };
+
+    let mut results: Vec<TypstResult> = (0..job_count)
+        .into_par_iter()
+        .map(|i| {
+            let source_bytes = unsafe { slice::from_raw_parts(source_ptrs[i], source_lens[i]) };
+            let source_text = match std::str::from_utf8(source_bytes) {
+                Ok(s) => s.to_string(),
+                Err(e) => return make_error(format!("invalid UTF-8 input: {}", e)),
+            };
+
+            let root = unsafe { parse_optional_path(root_ptrs[i], root_lens[i]) };
+            let package_cache = unsafe { parse_optional_path(pkg_ptrs[i], pkg_lens[i]) };
+
+            let world = SingleSourceWorld::new(
+                shared,
+                source_text,
+                root,
+                package_cache,
+                allow_package_download,
+                has_source_date_epoch.then_some(source_date_epoch),
+            );
+            dispatch_format(&world, format, pixels_per_point, structured_diagnostics)
+        })
+        .collect();
+
+    let count = results.len();
+    let ptr = results.as_mut_ptr();
+    std::mem::forget(results);
+    TypstResults {
+        results: ptr,
+        count,
+    }
+}
+
+/// Free an array of results allocated by `typst_world_compile_batch`.
+///
+/// # Safety
+/// `results` and `count` must come from a previous `TypstResults`. Each
+/// individual `TypstResult`'s buffers are freed the same way
+/// `typst_free_result` would free them.
+#[no_mangle]
+pub unsafe extern "C" fn typst_free_results(results: *mut TypstResult, count: usize) {
+    if results.is_null() || count == 0 {
+        return;
+    }
+    let results = unsafe { Vec::from_raw_parts(results, count, count) };
+    for result in results {
+        unsafe {
+            typst_free_result(
+                result.data,
+                result.len,
+                result.page_count,
+                result.page_lengths,
+                result.diagnostics,
+                result.diagnostics_len,
+            );
+        }
+    }
+}
+
+/// Leak a successful single-buffer output into C-owned memory for Go to read and free.
+fn leak_bytes(bytes: Vec<u8>) -> TypstResult {
+    let mut boxed = bytes.into_boxed_slice();
+    let ptr = boxed.as_mut_ptr();
+    let len = boxed.len();
+    std::mem::forget(boxed);
+    TypstResult {
+        data: ptr,
+        len,
+        error: 0,
+        page_count: 0,
+        page_lengths: std::ptr::null_mut(),
+        diagnostics: std::ptr::null_mut(),
+        diagnostics_len: 0,
+    }
+}
+
+/// Leak a successful per-page output into C-owned memory: `pages[i]` is concatenated
+/// into `data` in order, with `page_lengths[i]` recording each page's byte length.
+fn leak_pages(pages: Vec<Vec<u8>>) -> TypstResult {
+    let page_count = pages.len();
+    let mut lengths: Vec<u64> = Vec::with_capacity(page_count);
+    let mut data = Vec::with_capacity(pages.iter().map(Vec::len).sum());
+    for page in pages {
+        lengths.push(page.len() as u64);
+        data.extend_from_slice(&page);
+    }
+
+    let mut data = data.into_boxed_slice();
+    let ptr = data.as_mut_ptr();
+    let len = data.len();
+    std::mem::forget(data);
+
+    let mut lengths = lengths.into_boxed_slice();
+    let lengths_ptr = lengths.as_mut_ptr();
+    std::mem::forget(lengths);
+
+    TypstResult {
+        data: ptr,
+        len,
+        error: 0,
+        page_count,
+        page_lengths: lengths_ptr,
+        diagnostics: std::ptr::null_mut(),
+        diagnostics_len: 0,
+    }
 }
 
 /// Convert an error message into a TypstResult with error flag set.
@@ -362,5 +1059,9 @@ fn make_error(msg: String) -> TypstResult {
         data: ptr,
         len,
         error: 1,
+        page_count: 0,
+        page_lengths: std::ptr::null_mut(),
+        diagnostics: std::ptr::null_mut(),
+        diagnostics_len: 0,
     }
 }